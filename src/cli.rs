@@ -0,0 +1,48 @@
+use clap::{Parser, Subcommand};
+
+use crate::structs::magnet::MagnetLink;
+
+#[derive(Parser)]
+#[command(author, version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub subcmd: Commands,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    Decode {
+        encoded_value: String,
+    },
+    Info {
+        torrent_file: String,
+    },
+    Peers {
+        torrent_file: String,
+    },
+    Handshake {
+        torrent_file: String,
+        peer_address: String,
+    },
+    DownloadPiece {
+        #[arg(short)]
+        output: String,
+        torrent_file: String,
+        piece_index: usize,
+    },
+    Download {
+        #[arg(short)]
+        output: String,
+        torrent_file: String,
+    },
+    MagnetParse {
+        magnet_link: MagnetLink,
+    },
+    /// Downloads a torrent's full contents starting from just a magnet
+    /// link, via BEP 9 metadata exchange and BEP 10 extended messages.
+    MagnetDownload {
+        #[arg(short)]
+        output: String,
+        magnet_link: MagnetLink,
+    },
+}
@@ -0,0 +1,3 @@
+pub mod cli;
+pub mod structs;
+pub mod utils;
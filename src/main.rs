@@ -3,9 +3,8 @@ use bittorrent_starter_rust::cli::{Cli, Commands};
 use bittorrent_starter_rust::structs::peers::{Peer, PeerList};
 use bittorrent_starter_rust::structs::torrent::Torrent;
 use bittorrent_starter_rust::utils::decoder::decode_bencoded_value;
-use bittorrent_starter_rust::utils::files::write_file;
+use bittorrent_starter_rust::utils::files::{write_file, write_torrent_output};
 use clap::Parser;
-use serde_bencode::from_bytes;
 use std::fs;
 
 #[allow(dead_code)]
@@ -16,13 +15,17 @@ async fn main() -> Result<(), Error> {
     match args.subcmd {
         Commands::Decode { encoded_value } => {
             let (decoded_value, _) = decode_bencoded_value(&encoded_value);
-            println!("{}", decoded_value.to_string());
+            println!("{decoded_value}");
         }
         Commands::Info { torrent_file } => {
             let file = fs::read(torrent_file).context("Reading torrent file")?;
-            let torrent: Torrent = from_bytes(&file).context("Parsing file content")?;
-            println!("Tracker URL: {}", torrent.announce);
-            println!("Length: {}", torrent.info.length);
+            let torrent = Torrent::parse(&file).context("Parsing file content")?;
+            torrent.validate()?;
+            println!(
+                "Tracker URL: {}",
+                torrent.announce.as_deref().unwrap_or("(trackerless)")
+            );
+            println!("Length: {}", torrent.info.length());
             let torrent_hash = torrent.info_hash();
             println!("Info Hash: {}", hex::encode(torrent_hash));
             println!("Piece Length: {}", torrent.info.piece_length);
@@ -33,15 +36,16 @@ async fn main() -> Result<(), Error> {
         }
         Commands::Peers { torrent_file } => {
             let file = fs::read(torrent_file).context("Reading torrent file")?;
-            let torrent: Torrent = from_bytes(&file).context("Parsing file content")?;
-            PeerList::get_peers(&torrent).await?;
+            let mut torrent = Torrent::parse(&file).context("Parsing file content")?;
+            torrent.validate()?;
+            PeerList::get_peers(&mut torrent).await?;
         }
         Commands::Handshake {
             torrent_file,
             peer_address,
         } => {
             let file = fs::read(torrent_file).context("Reading torrent file")?;
-            let torrent: Torrent = from_bytes(&file).context("Parsing file content")?;
+            let torrent = Torrent::parse(&file).context("Parsing file content")?;
             let info_hash = torrent.info_hash();
             Peer::new(peer_address, &info_hash).await?;
         }
@@ -51,14 +55,15 @@ async fn main() -> Result<(), Error> {
             output,
         } => {
             let file = fs::read(torrent_file).context("Reading torrent file")?;
-            let torrent: Torrent = from_bytes(&file).context("Parsing file content")?;
+            let mut torrent = Torrent::parse(&file).context("Parsing file content")?;
+            torrent.validate()?;
             let mut available_peers: Vec<Peer> = torrent.get_available_peers().await?;
 
-            println!("Torrent length: {}", torrent.info.length);
+            println!("Torrent length: {}", torrent.info.length());
             let piece_len = torrent.get_piece_len(piece_index);
             let mut file_data = vec![0u8; piece_len as usize]; // for the purpose of this test, this needs to be the piece size
-            let data = available_peers[1]
-                .download_piece(piece_index, piece_len)
+            let data = torrent
+                .download_piece_verified(&mut available_peers, piece_index, piece_len)
                 .await?;
 
             if data.len() != piece_len as usize {
@@ -73,14 +78,15 @@ async fn main() -> Result<(), Error> {
             output,
         } => {
             let file = fs::read(torrent_file).context("Reading torrent file")?;
-            let mut torrent: Torrent = from_bytes(&file).context("Parsing file content")?;
+            let mut torrent = Torrent::parse(&file).context("Parsing file content")?;
+            torrent.validate()?;
             if let Ok(pieces) = torrent.download_torrent().await {
                 let data = pieces.into_iter().flatten().collect::<Vec<u8>>();
-                if data.len() != torrent.info.length as usize {
+                if data.len() != torrent.info.length() as usize {
                     eprintln!("Error downloading torrent: invalid length");
                     return Ok(());
                 }
-                write_file(&output, &data)?;
+                write_torrent_output(&output, &torrent.info, &data)?;
                 println!("File saved to {}", output);
             } else {
                 eprintln!("Error downloading torrent");
@@ -90,6 +96,26 @@ async fn main() -> Result<(), Error> {
             println!("Tracker URL: {}", magnet_link.tracker_url);
             println!("Info Hash: {}", hex::encode(magnet_link.info_hash));
         }
+        Commands::MagnetDownload {
+            magnet_link,
+            output,
+        } => {
+            let mut torrent = magnet_link
+                .fetch_torrent()
+                .await
+                .context("bootstrapping torrent from magnet link")?;
+            if let Ok(pieces) = torrent.download_torrent().await {
+                let data = pieces.into_iter().flatten().collect::<Vec<u8>>();
+                if data.len() != torrent.info.length() as usize {
+                    eprintln!("Error downloading torrent: invalid length");
+                    return Ok(());
+                }
+                write_torrent_output(&output, &torrent.info, &data)?;
+                println!("File saved to {}", output);
+            } else {
+                eprintln!("Error downloading torrent");
+            }
+        }
     };
 
     Ok(())
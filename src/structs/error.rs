@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+/// Errors from parsing and validating a `.torrent` file, surfaced before a
+/// download is attempted so malformed-but-parseable torrents fail with an
+/// actionable message instead of a confusing failure partway through.
+#[derive(Debug, Error)]
+pub enum TorrentError {
+    #[error("torrent file is not valid bencode: {0}")]
+    InvalidBencode(#[from] serde_bencode::Error),
+
+    #[error(
+        "info.pieces is {actual} bytes long, which is not a multiple of 20 (the SHA1 hash size)"
+    )]
+    PiecesLengthNotMultipleOf20 { actual: usize },
+
+    #[error("info.pieces is empty; torrent has no pieces to download")]
+    EmptyPieces,
+
+    #[error("torrent has no announce URL and no DHT nodes to discover peers with")]
+    EmptyAnnounceAndNoNodes,
+
+    #[error("info.piece_length must be positive, got {actual}")]
+    NonPositivePieceLength { actual: u64 },
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Context, Result};
+use std::str::FromStr;
+
+use crate::structs::peers::{Peer, PeerList};
+use crate::structs::torrent::Torrent;
+
+/// A parsed `magnet:?xt=urn:btih:...&dn=...&tr=...` link (BEP 9).
+#[derive(Debug, Clone)]
+pub struct MagnetLink {
+    pub info_hash: [u8; 20],
+    pub tracker_url: String,
+    pub display_name: Option<String>,
+}
+
+impl FromStr for MagnetLink {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let query = s
+            .strip_prefix("magnet:?")
+            .ok_or_else(|| anyhow!("not a magnet link: {s}"))?;
+
+        let mut info_hash = None;
+        let mut tracker_url = None;
+        let mut display_name = None;
+
+        for pair in query.split('&') {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("malformed magnet parameter: {pair}"))?;
+            let value = urlencoding::decode(value)
+                .with_context(|| format!("invalid percent-encoding in {key}"))?
+                .into_owned();
+
+            match key {
+                "xt" => {
+                    let hex_hash = value
+                        .strip_prefix("urn:btih:")
+                        .ok_or_else(|| anyhow!("unsupported xt value: {value}"))?;
+                    let bytes = hex::decode(hex_hash).context("decoding info hash hex")?;
+                    let hash: [u8; 20] = bytes
+                        .try_into()
+                        .map_err(|_| anyhow!("info hash must be 20 bytes"))?;
+                    info_hash = Some(hash);
+                }
+                "tr" => tracker_url = Some(value),
+                "dn" => display_name = Some(value),
+                _ => {}
+            }
+        }
+
+        Ok(MagnetLink {
+            info_hash: info_hash.context("magnet link is missing an xt=urn:btih: parameter")?,
+            tracker_url: tracker_url.context("magnet link is missing a tr= tracker parameter")?,
+            display_name,
+        })
+    }
+}
+
+impl MagnetLink {
+    /// Bootstraps a full `Torrent` from just the magnet link's info hash:
+    /// connects to a peer from the tracker's peer list, performs the BEP 10
+    /// extended handshake, and fetches the `info` dict over `ut_metadata`
+    /// (BEP 9).
+    pub async fn fetch_torrent(&self) -> Result<Torrent> {
+        let addrs = PeerList::fetch_peer_addrs(&self.tracker_url, &self.info_hash, 0)
+            .await
+            .context("announcing to tracker")?;
+        let addr = addrs.first().context("tracker returned no peers")?;
+
+        let mut peer = Peer::new(addr.to_string(), &self.info_hash)
+            .await
+            .with_context(|| format!("connecting to peer {addr}"))?;
+        let (ut_metadata_id, metadata_size) = peer
+            .extension_handshake()
+            .await
+            .context("performing extended handshake")?;
+        let info = peer
+            .request_metadata(ut_metadata_id, metadata_size, &self.info_hash)
+            .await
+            .context("downloading metadata")?;
+
+        let torrent = Torrent {
+            announce: Some(self.tracker_url.clone()),
+            announce_list: None,
+            nodes: None,
+            info,
+        };
+        // `info` just came from an untrusted peer over the wire; validate it
+        // the same way a parsed .torrent file is validated before it's used
+        // (e.g. a malicious `piece length: 0` would otherwise divide by zero
+        // in `Torrent::get_piece_len`).
+        torrent
+            .validate()
+            .context("peer sent an invalid metadata info dict")?;
+        Ok(torrent)
+    }
+}
@@ -0,0 +1,5 @@
+pub mod error;
+pub mod magnet;
+pub mod peers;
+pub mod torrent;
+pub mod udp_tracker;
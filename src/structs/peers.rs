@@ -0,0 +1,473 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use sha1::{Digest, Sha1};
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::structs::torrent::Info;
+
+const PEER_ID: &[u8; 20] = b"00112233445566778899";
+const PROTOCOL: &[u8; 19] = b"BitTorrent protocol";
+const BLOCK_SIZE: u32 = 16 * 1024;
+const DEFAULT_PIPELINE_DEPTH: usize = 5;
+
+// Message ids from the base wire protocol.
+const MSG_UNCHOKE: u8 = 1;
+const MSG_INTERESTED: u8 = 2;
+const MSG_BITFIELD: u8 = 5;
+const MSG_REQUEST: u8 = 6;
+const MSG_PIECE: u8 = 7;
+const MSG_EXTENDED: u8 = 20;
+
+pub struct PeerList;
+
+#[derive(Debug, Deserialize)]
+struct TrackerResponse {
+    #[allow(dead_code)]
+    interval: u64,
+    peers: ByteBuf,
+}
+
+impl PeerList {
+    /// Announces to `torrent`'s trackers (honoring `announce-list` tiers and
+    /// failover, per BEP 12) and prints the peer list, as used by the
+    /// `Peers` command.
+    pub async fn get_peers(torrent: &mut crate::structs::torrent::Torrent) -> Result<Vec<SocketAddrV4>> {
+        let info_hash = torrent.info_hash();
+        let length = torrent.info.length();
+        let tiers = torrent.tiers();
+        let (addrs, working_tracker) = Self::fetch_peer_addrs_tiered(&tiers, &info_hash, length).await?;
+        torrent.promote_tracker(&working_tracker);
+
+        for addr in &addrs {
+            println!("{addr}");
+        }
+        Ok(addrs)
+    }
+
+    /// Tries each tracker in each tier in order, returning the peer list
+    /// from the first one that responds along with its URL (so the caller
+    /// can promote it to the front of its tier for subsequent announces).
+    pub async fn fetch_peer_addrs_tiered(
+        tiers: &[Vec<String>],
+        info_hash: &[u8; 20],
+        left: u64,
+    ) -> Result<(Vec<SocketAddrV4>, String)> {
+        let mut last_err = None;
+        for tier in tiers {
+            for tracker in tier {
+                match Self::fetch_peer_addrs(tracker, info_hash, left).await {
+                    Ok(addrs) => return Ok((addrs, tracker.clone())),
+                    Err(err) => {
+                        eprintln!("Tracker {tracker} failed: {err}");
+                        last_err = Some(err);
+                    }
+                }
+            }
+        }
+        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("no trackers configured")))
+            .context("all trackers in announce-list failed")
+    }
+
+    /// Announces to a single tracker and returns its compact peer list.
+    /// `left` is the number of bytes remaining to download; for a magnet
+    /// link whose length isn't known yet, pass `0`. Dispatches to the BEP 15
+    /// UDP tracker client for `udp://` announce URLs, and to the HTTP(S)
+    /// tracker protocol otherwise.
+    pub async fn fetch_peer_addrs(announce: &str, info_hash: &[u8; 20], left: u64) -> Result<Vec<SocketAddrV4>> {
+        if announce.starts_with("udp://") {
+            return crate::structs::udp_tracker::announce(announce, info_hash, left)
+                .await
+                .context("announcing to udp tracker");
+        }
+
+        let url = Self::build_announce_url(announce, info_hash, left);
+        let response = reqwest::get(url).await.context("announcing to tracker")?;
+        let body = response.bytes().await.context("reading tracker response")?;
+        let response: TrackerResponse =
+            serde_bencode::from_bytes(&body).context("parsing tracker response")?;
+
+        Self::parse_compact_peers(&response.peers)
+    }
+
+    fn build_announce_url(announce: &str, info_hash: &[u8; 20], left: u64) -> String {
+        format!(
+            "{announce}?info_hash={}&peer_id={}&port=6881&uploaded=0&downloaded=0&left={left}&compact=1",
+            Self::url_encode(info_hash),
+            String::from_utf8_lossy(PEER_ID)
+        )
+    }
+
+    fn url_encode(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("%{:02x}", b)).collect()
+    }
+
+    fn parse_compact_peers(raw: &[u8]) -> Result<Vec<SocketAddrV4>> {
+        if !raw.len().is_multiple_of(6) {
+            bail!("compact peers list length {} is not a multiple of 6", raw.len());
+        }
+        Ok(raw
+            .chunks(6)
+            .map(|chunk| {
+                SocketAddrV4::new(
+                    Ipv4Addr::new(chunk[0], chunk[1], chunk[2], chunk[3]),
+                    u16::from_be_bytes([chunk[4], chunk[5]]),
+                )
+            })
+            .collect())
+    }
+}
+
+/// Header bencode dict for a BEP 10 extended handshake (message id 20,
+/// extended message id 0).
+#[derive(Debug, Serialize, Deserialize)]
+struct ExtensionHandshake {
+    m: ExtensionDict,
+    #[serde(default)]
+    metadata_size: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ExtensionDict {
+    ut_metadata: u8,
+}
+
+/// Header bencode dict in front of a `ut_metadata` request/data message.
+#[derive(Debug, Serialize, Deserialize)]
+struct MetadataMessage {
+    msg_type: u8,
+    piece: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_size: Option<usize>,
+}
+
+pub struct Peer {
+    stream: TcpStream,
+    pub peer_id: [u8; 20],
+    pub bitfield: Vec<u8>,
+    reserved: [u8; 8],
+}
+
+impl Peer {
+    /// Connects to `peer_address` and performs the base BitTorrent
+    /// handshake, advertising support for the BEP 10 extension protocol.
+    pub async fn new(peer_address: String, info_hash: &[u8; 20]) -> Result<Self> {
+        let mut stream = TcpStream::connect(&peer_address)
+            .await
+            .with_context(|| format!("connecting to peer {peer_address}"))?;
+
+        let mut handshake = [0u8; 68];
+        handshake[0] = 19;
+        handshake[1..20].copy_from_slice(PROTOCOL);
+        handshake[25] |= 0x10; // bit 20 from the right: extension protocol (BEP 10)
+        handshake[28..48].copy_from_slice(info_hash);
+        handshake[48..68].copy_from_slice(PEER_ID);
+        stream
+            .write_all(&handshake)
+            .await
+            .context("sending handshake")?;
+
+        let mut response = [0u8; 68];
+        stream
+            .read_exact(&mut response)
+            .await
+            .context("reading handshake response")?;
+        println!("Peer ID: {}", hex::encode(&response[48..68]));
+
+        let mut reserved = [0u8; 8];
+        reserved.copy_from_slice(&response[20..28]);
+        let mut peer_id = [0u8; 20];
+        peer_id.copy_from_slice(&response[48..68]);
+
+        let mut peer = Peer {
+            stream,
+            peer_id,
+            bitfield: Vec::new(),
+            reserved,
+        };
+        peer.receive_bitfield().await?;
+        peer.send_interested().await?;
+        peer.wait_for_unchoke().await?;
+        Ok(peer)
+    }
+
+    pub fn supports_extensions(&self) -> bool {
+        self.reserved[5] & 0x10 != 0
+    }
+
+    async fn read_message(&mut self) -> Result<(u8, Vec<u8>)> {
+        let mut len_buf = [0u8; 4];
+        self.stream
+            .read_exact(&mut len_buf)
+            .await
+            .context("reading message length")?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+        if len == 0 {
+            // Keep-alive; no id byte follows. Recurse for the next real message.
+            return Box::pin(self.read_message()).await;
+        }
+        let mut buf = vec![0u8; len];
+        self.stream
+            .read_exact(&mut buf)
+            .await
+            .context("reading message body")?;
+        Ok((buf[0], buf[1..].to_vec()))
+    }
+
+    async fn send_message(&mut self, id: u8, payload: &[u8]) -> Result<()> {
+        let len = (payload.len() + 1) as u32;
+        let mut message = Vec::with_capacity(4 + payload.len() + 1);
+        message.extend_from_slice(&len.to_be_bytes());
+        message.push(id);
+        message.extend_from_slice(payload);
+        self.stream
+            .write_all(&message)
+            .await
+            .context("sending message")?;
+        Ok(())
+    }
+
+    async fn receive_bitfield(&mut self) -> Result<()> {
+        let (id, payload) = self.read_message().await?;
+        if id != MSG_BITFIELD {
+            bail!("expected bitfield message (id {MSG_BITFIELD}), got id {id}");
+        }
+        self.bitfield = payload;
+        Ok(())
+    }
+
+    async fn send_interested(&mut self) -> Result<()> {
+        self.send_message(MSG_INTERESTED, &[]).await
+    }
+
+    async fn wait_for_unchoke(&mut self) -> Result<()> {
+        loop {
+            let (id, _) = self.read_message().await?;
+            if id == MSG_UNCHOKE {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Downloads a whole piece, keeping up to [`DEFAULT_PIPELINE_DEPTH`]
+    /// block `request`s outstanding at once instead of waiting for each
+    /// block before requesting the next.
+    pub async fn download_piece(&mut self, piece_index: usize, piece_len: u64) -> Result<Vec<u8>> {
+        self.download_piece_pipelined(piece_index, piece_len, DEFAULT_PIPELINE_DEPTH)
+            .await
+    }
+
+    /// Same as [`Peer::download_piece`], with a caller-chosen pipeline
+    /// depth (how many `request` messages may be outstanding at once).
+    pub async fn download_piece_pipelined(
+        &mut self,
+        piece_index: usize,
+        piece_len: u64,
+        depth: usize,
+    ) -> Result<Vec<u8>> {
+        let piece_len = piece_len as u32;
+        let mut data = vec![0u8; piece_len as usize];
+
+        let mut next_offset = 0u32;
+        let mut outstanding: VecDeque<u32> = VecDeque::with_capacity(depth);
+        let mut received = 0u32;
+
+        while received < piece_len {
+            while outstanding.len() < depth && next_offset < piece_len {
+                let block_len = BLOCK_SIZE.min(piece_len - next_offset);
+                let mut request = Vec::with_capacity(12);
+                request.extend_from_slice(&(piece_index as u32).to_be_bytes());
+                request.extend_from_slice(&next_offset.to_be_bytes());
+                request.extend_from_slice(&block_len.to_be_bytes());
+                self.send_message(MSG_REQUEST, &request).await?;
+
+                outstanding.push_back(next_offset);
+                next_offset += block_len;
+            }
+
+            let (id, payload) = self.read_message().await?;
+            if id != MSG_PIECE {
+                bail!("expected piece message (id {MSG_PIECE}), got id {id}");
+            }
+            if payload.len() < 8 {
+                bail!("piece message payload is only {} bytes, too short to contain a header", payload.len());
+            }
+            let begin = u32::from_be_bytes(payload[4..8].try_into().unwrap());
+            // Only count this block if it was actually one we were waiting
+            // on: a duplicate/retransmitted `piece` message for a block
+            // already received must not count twice, or `received` could
+            // reach `piece_len` while a real outstanding block never
+            // arrives, silently leaving a zero-filled gap in `data`.
+            if let Some(pos) = outstanding.iter().position(|&offset| offset == begin) {
+                let block = &payload[8..];
+                let end = begin as usize + block.len();
+                if end > data.len() {
+                    bail!(
+                        "piece message block at offset {begin} (len {}) overruns the {piece_len}-byte piece",
+                        block.len()
+                    );
+                }
+                outstanding.remove(pos);
+                data[begin as usize..end].copy_from_slice(block);
+                received += block.len() as u32;
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Performs the BEP 10 extended handshake and returns the peer's
+    /// `ut_metadata` message id together with the total metadata size in
+    /// bytes, as reported by the peer.
+    pub async fn extension_handshake(&mut self) -> Result<(u8, usize)> {
+        if !self.supports_extensions() {
+            bail!("peer does not support the extension protocol");
+        }
+
+        let handshake = ExtensionHandshake {
+            m: ExtensionDict { ut_metadata: 1 },
+            metadata_size: None,
+        };
+        let mut payload = vec![0u8]; // extended message id 0 == handshake
+        payload.extend(serde_bencode::to_bytes(&handshake)?);
+        self.send_message(MSG_EXTENDED, &payload).await?;
+
+        loop {
+            let (id, payload) = self.read_message().await?;
+            if id != MSG_EXTENDED || payload.first() != Some(&0) {
+                continue;
+            }
+            let reply: ExtensionHandshake =
+                serde_bencode::from_bytes(&payload[1..]).context("parsing extended handshake")?;
+            let metadata_size = reply
+                .metadata_size
+                .context("peer's extended handshake did not report metadata_size")?;
+            return Ok((reply.m.ut_metadata, metadata_size));
+        }
+    }
+
+    /// Downloads the torrent's `info` dict in 16 KiB blocks via `ut_metadata`
+    /// (BEP 9) and verifies it against `info_hash` before parsing it.
+    pub async fn request_metadata(
+        &mut self,
+        ut_metadata_id: u8,
+        metadata_size: usize,
+        info_hash: &[u8; 20],
+    ) -> Result<Info> {
+        let total_blocks = metadata_size.div_ceil(BLOCK_SIZE as usize);
+        let mut blocks = Vec::with_capacity(total_blocks);
+
+        for piece in 0..total_blocks {
+            let request = MetadataMessage {
+                msg_type: 0,
+                piece,
+                total_size: None,
+            };
+            let mut payload = vec![ut_metadata_id];
+            payload.extend(serde_bencode::to_bytes(&request)?);
+            self.send_message(MSG_EXTENDED, &payload).await?;
+
+            blocks.push(self.read_metadata_block(ut_metadata_id, piece).await?);
+        }
+
+        let metadata: Vec<u8> = blocks.into_iter().flatten().collect();
+
+        let mut hasher = Sha1::new();
+        hasher.update(&metadata);
+        let actual_hash: [u8; 20] = hasher.finalize().into();
+        if &actual_hash != info_hash {
+            bail!(
+                "metadata hash mismatch: expected {}, got {}",
+                hex::encode(info_hash),
+                hex::encode(actual_hash)
+            );
+        }
+
+        serde_bencode::from_bytes(&metadata).context("parsing metadata info dict")
+    }
+
+    async fn read_metadata_block(&mut self, ut_metadata_id: u8, expected_piece: usize) -> Result<Vec<u8>> {
+        loop {
+            let (id, payload) = self.read_message().await?;
+            if id != MSG_EXTENDED || payload.first() != Some(&ut_metadata_id) {
+                continue;
+            }
+            let body = &payload[1..];
+            let header_len = bencode_value_len(body)?;
+            let header: MetadataMessage = serde_bencode::from_bytes(&body[..header_len])
+                .context("parsing ut_metadata piece header")?;
+            if header.msg_type != 1 || header.piece != expected_piece {
+                continue;
+            }
+            return Ok(body[header_len..].to_vec());
+        }
+    }
+}
+
+/// Returns the byte length of the single bencode value at the start of
+/// `buf`. Used to find where a `ut_metadata` message's bencoded header ends
+/// and its raw piece bytes begin, without a full bencode parse of the tail.
+fn bencode_value_len(buf: &[u8]) -> Result<usize> {
+    match buf.first() {
+        Some(b'i') => {
+            let end = buf.iter().position(|&b| b == b'e').context("unterminated integer")?;
+            Ok(end + 1)
+        }
+        Some(b'l') | Some(b'd') => {
+            let mut pos = 1;
+            while buf.get(pos) != Some(&b'e') {
+                if pos >= buf.len() {
+                    bail!("unterminated list/dict");
+                }
+                pos += bencode_value_len(&buf[pos..])?;
+            }
+            Ok(pos + 1)
+        }
+        Some(b'0'..=b'9') => {
+            let colon = buf.iter().position(|&b| b == b':').context("malformed string length")?;
+            let len: usize = std::str::from_utf8(&buf[..colon])?.parse()?;
+            Ok(colon + 1 + len)
+        }
+        _ => bail!("unexpected bencode token"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bencode_value_len_integer() {
+        assert_eq!(bencode_value_len(b"i42e").unwrap(), 4);
+    }
+
+    #[test]
+    fn bencode_value_len_string() {
+        assert_eq!(bencode_value_len(b"4:spam").unwrap(), 6);
+    }
+
+    #[test]
+    fn bencode_value_len_stops_at_end_of_dict_ignoring_trailing_bytes() {
+        let header = b"d8:msg_typei1e5:piecei0ee";
+        let mut buf = header.to_vec();
+        buf.extend_from_slice(b"raw piece bytes follow here");
+        assert_eq!(bencode_value_len(&buf).unwrap(), header.len());
+    }
+
+    #[test]
+    fn parse_compact_peers_splits_into_6_byte_chunks() {
+        let raw = [127, 0, 0, 1, 0x1a, 0xe1, 10, 0, 0, 1, 0x1a, 0xe2];
+        let peers = PeerList::parse_compact_peers(&raw).unwrap();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].to_string(), "127.0.0.1:6881");
+        assert_eq!(peers[1].to_string(), "10.0.0.1:6882");
+    }
+
+    #[test]
+    fn parse_compact_peers_rejects_length_not_a_multiple_of_6() {
+        assert!(PeerList::parse_compact_peers(&[0u8; 7]).is_err());
+    }
+}
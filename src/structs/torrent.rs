@@ -0,0 +1,349 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use sha1::{Digest, Sha1};
+
+use crate::structs::error::TorrentError;
+use crate::structs::peers::{Peer, PeerList};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Torrent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub announce: Option<String>,
+    #[serde(rename = "announce-list", default, skip_serializing_if = "Option::is_none")]
+    pub announce_list: Option<Vec<Vec<String>>>,
+    /// BEP 5 DHT bootstrap nodes, present instead of `announce` on a fully
+    /// trackerless torrent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<Vec<(String, u16)>>,
+    pub info: Info,
+}
+
+// Field order matters here: serde_bencode serializes a struct's fields in
+// declaration order, and `info_hash` depends on that order matching the
+// dictionary's required lexicographic key order. `keys` is flattened in
+// first so that both variants land in the right spot ("files" and "length"
+// both sort before "name", "piece length" and "pieces").
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Info {
+    #[serde(flatten)]
+    pub keys: Keys,
+    pub name: String,
+    #[serde(rename = "piece length")]
+    pub piece_length: u64,
+    pub pieces: ByteBuf,
+}
+
+/// A torrent's `info` dict is either single-file (a top-level `length`) or
+/// multi-file (a `files` list), per BEP 3.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Keys {
+    SingleFile { length: u64 },
+    MultiFile { files: Vec<FileInfo> },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FileInfo {
+    pub length: u64,
+    pub path: Vec<String>,
+}
+
+impl Info {
+    /// Total size of the torrent's content in bytes: `length` for a
+    /// single-file torrent, or the sum of all `files[].length` otherwise.
+    pub fn length(&self) -> u64 {
+        match &self.keys {
+            Keys::SingleFile { length } => *length,
+            Keys::MultiFile { files } => files.iter().map(|f| f.length).sum(),
+        }
+    }
+
+    /// How many pieces this torrent is split into, per `pieces`' length.
+    pub fn piece_count(&self) -> usize {
+        self.pieces.len() / 20
+    }
+
+    /// The expected 20-byte SHA1 hash for `piece_index`, sliced out of the
+    /// concatenated `pieces` field.
+    pub fn piece_hash(&self, piece_index: usize) -> &[u8] {
+        &self.pieces[piece_index * 20..piece_index * 20 + 20]
+    }
+}
+
+/// How many attempts in all (the first one included) to make at downloading
+/// a piece, each from the next peer in rotation, before giving up on it.
+const MAX_PIECE_RETRIES: usize = 5;
+
+impl Torrent {
+    /// Parses a `.torrent` file's bencode, surfacing failures as a typed
+    /// [`TorrentError::InvalidBencode`] instead of an opaque error.
+    pub fn parse(bytes: &[u8]) -> Result<Self, TorrentError> {
+        serde_bencode::from_bytes(bytes).map_err(TorrentError::from)
+    }
+
+    /// Checks that a successfully-parsed torrent is actually well-formed,
+    /// so malformed-but-parseable torrents are rejected up front rather than
+    /// failing confusingly during download.
+    pub fn validate(&self) -> Result<(), TorrentError> {
+        if self.info.pieces.is_empty() {
+            return Err(TorrentError::EmptyPieces);
+        }
+        if !self.info.pieces.len().is_multiple_of(20) {
+            return Err(TorrentError::PiecesLengthNotMultipleOf20 {
+                actual: self.info.pieces.len(),
+            });
+        }
+        if self.info.piece_length == 0 {
+            return Err(TorrentError::NonPositivePieceLength {
+                actual: self.info.piece_length,
+            });
+        }
+        let has_tracker = self.announce.as_deref().is_some_and(|a| !a.is_empty())
+            || self
+                .announce_list
+                .as_ref()
+                .is_some_and(|tiers| tiers.iter().flatten().any(|t| !t.is_empty()));
+        let has_nodes = self.nodes.as_ref().is_some_and(|nodes| !nodes.is_empty());
+        if !has_tracker && !has_nodes {
+            return Err(TorrentError::EmptyAnnounceAndNoNodes);
+        }
+        Ok(())
+    }
+
+    pub fn info_hash(&self) -> [u8; 20] {
+        let bencoded = serde_bencode::to_bytes(&self.info).expect("info dict is always valid bencode");
+        let mut hasher = Sha1::new();
+        hasher.update(&bencoded);
+        hasher.finalize().into()
+    }
+
+    pub fn get_piece_len(&self, piece_index: usize) -> u64 {
+        let num_pieces = self.info.piece_count();
+        if piece_index == num_pieces - 1 {
+            let remainder = self.info.length() % self.info.piece_length;
+            if remainder == 0 {
+                self.info.piece_length
+            } else {
+                remainder
+            }
+        } else {
+            self.info.piece_length
+        }
+    }
+
+    /// Tracker tiers to announce to, per BEP 12: the parsed `announce-list`
+    /// if present, otherwise a single tier containing just `announce`. Empty
+    /// for a trackerless (DHT-only) torrent — discovering peers via `nodes`
+    /// isn't implemented, so such a torrent simply has no trackers to try.
+    pub(crate) fn tiers(&self) -> Vec<Vec<String>> {
+        match &self.announce_list {
+            Some(list) if !list.is_empty() => list.clone(),
+            _ => match &self.announce {
+                Some(announce) if !announce.is_empty() => vec![vec![announce.clone()]],
+                _ => Vec::new(),
+            },
+        }
+    }
+
+    /// Records `tracker` as the preferred choice for future announces by
+    /// promoting it to the front of whichever tier it's found in.
+    pub(crate) fn promote_tracker(&mut self, tracker: &str) {
+        let Some(list) = &mut self.announce_list else {
+            return;
+        };
+        for tier in list {
+            if let Some(pos) = tier.iter().position(|t| t == tracker) {
+                tier.swap(0, pos);
+                return;
+            }
+        }
+    }
+
+    pub async fn get_available_peers(&mut self) -> Result<Vec<Peer>> {
+        let info_hash = self.info_hash();
+        let length = self.info.length();
+        let tiers = self.tiers();
+        let (addrs, working_tracker) = PeerList::fetch_peer_addrs_tiered(&tiers, &info_hash, length).await?;
+        self.promote_tracker(&working_tracker);
+
+        let mut peers = Vec::with_capacity(addrs.len());
+        for addr in addrs {
+            match Peer::new(addr.to_string(), &info_hash).await {
+                Ok(peer) => peers.push(peer),
+                Err(err) => eprintln!("Skipping peer {addr}: {err}"),
+            }
+        }
+        Ok(peers)
+    }
+
+    pub async fn download_torrent(&mut self) -> Result<Vec<Vec<u8>>> {
+        let mut peers = self
+            .get_available_peers()
+            .await
+            .context("connecting to peers")?;
+        if peers.is_empty() {
+            anyhow::bail!("no peers available to download from");
+        }
+
+        let num_pieces = self.info.pieces.len() / 20;
+        let mut pieces = Vec::with_capacity(num_pieces);
+        for piece_index in 0..num_pieces {
+            let piece_len = self.get_piece_len(piece_index);
+            let data = self
+                .download_piece_verified(&mut peers, piece_index, piece_len)
+                .await?;
+            pieces.push(data);
+        }
+        Ok(pieces)
+    }
+
+    /// Downloads a single piece and verifies its SHA1 against
+    /// `info.pieces`, re-requesting it (from the next peer in `peers`, in
+    /// rotation) on mismatch or error, for at most [`MAX_PIECE_RETRIES`]
+    /// attempts in all.
+    pub async fn download_piece_verified(
+        &self,
+        peers: &mut [Peer],
+        piece_index: usize,
+        piece_len: u64,
+    ) -> Result<Vec<u8>> {
+        if peers.is_empty() {
+            anyhow::bail!("no peers available to download piece {piece_index}");
+        }
+        let piece_count = self.info.piece_count();
+        if piece_index >= piece_count {
+            anyhow::bail!("piece index {piece_index} out of range (torrent has {piece_count} pieces)");
+        }
+
+        let expected_hash = self.info.piece_hash(piece_index);
+        let mut last_err = None;
+
+        for attempt in 0..MAX_PIECE_RETRIES {
+            let peer = &mut peers[(piece_index + attempt) % peers.len()];
+            let data = match peer.download_piece(piece_index, piece_len).await {
+                Ok(data) => data,
+                Err(err) => {
+                    last_err = Some(err);
+                    continue;
+                }
+            };
+
+            let mut hasher = Sha1::new();
+            hasher.update(&data);
+            let actual_hash: [u8; 20] = hasher.finalize().into();
+            if actual_hash.as_slice() == expected_hash {
+                return Ok(data);
+            }
+            last_err = Some(anyhow::anyhow!(
+                "piece {piece_index} hash mismatch: expected {}, got {}",
+                hex::encode(expected_hash),
+                hex::encode(actual_hash)
+            ));
+        }
+
+        Err(last_err.unwrap_or_else(|| anyhow::anyhow!("no peers available for piece {piece_index}")))
+            .with_context(|| format!("downloading piece {piece_index} after {MAX_PIECE_RETRIES} attempts"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_torrent(total_length: u64, piece_length: u64, num_pieces: usize) -> Torrent {
+        Torrent {
+            announce: Some("http://tracker.example/announce".to_string()),
+            announce_list: None,
+            nodes: None,
+            info: Info {
+                keys: Keys::SingleFile { length: total_length },
+                name: "sample".to_string(),
+                piece_length,
+                pieces: ByteBuf::from(vec![0u8; num_pieces * 20]),
+            },
+        }
+    }
+
+    #[test]
+    fn info_length_sums_multi_file_sizes() {
+        let info = Info {
+            keys: Keys::MultiFile {
+                files: vec![
+                    FileInfo { length: 3, path: vec!["a.txt".to_string()] },
+                    FileInfo { length: 5, path: vec!["b.txt".to_string()] },
+                ],
+            },
+            name: "bundle".to_string(),
+            piece_length: 4,
+            pieces: ByteBuf::from(vec![0u8; 40]),
+        };
+        assert_eq!(info.length(), 8);
+    }
+
+    #[test]
+    fn get_piece_len_is_full_length_except_the_last_piece() {
+        let torrent = sample_torrent(10, 4, 3); // pieces of 4, 4, 2 bytes
+        assert_eq!(torrent.get_piece_len(0), 4);
+        assert_eq!(torrent.get_piece_len(1), 4);
+        assert_eq!(torrent.get_piece_len(2), 2);
+    }
+
+    #[test]
+    fn get_piece_len_last_piece_is_full_when_length_is_an_exact_multiple() {
+        let torrent = sample_torrent(8, 4, 2);
+        assert_eq!(torrent.get_piece_len(1), 4);
+    }
+
+    #[test]
+    fn tiers_prefers_announce_list_over_announce() {
+        let mut torrent = sample_torrent(8, 4, 2);
+        torrent.announce_list = Some(vec![
+            vec!["http://a".to_string(), "http://b".to_string()],
+            vec!["http://c".to_string()],
+        ]);
+        assert_eq!(
+            torrent.tiers(),
+            vec![
+                vec!["http://a".to_string(), "http://b".to_string()],
+                vec!["http://c".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn tiers_falls_back_to_announce_when_no_announce_list() {
+        let torrent = sample_torrent(8, 4, 2);
+        assert_eq!(torrent.tiers(), vec![vec!["http://tracker.example/announce".to_string()]]);
+    }
+
+    #[test]
+    fn tiers_is_empty_for_a_trackerless_torrent() {
+        let mut torrent = sample_torrent(8, 4, 2);
+        torrent.announce = None;
+        assert!(torrent.tiers().is_empty());
+    }
+
+    #[test]
+    fn promote_tracker_moves_the_working_tracker_to_the_front_of_its_tier() {
+        let mut torrent = sample_torrent(8, 4, 2);
+        torrent.announce_list = Some(vec![vec![
+            "http://a".to_string(),
+            "http://b".to_string(),
+            "http://c".to_string(),
+        ]]);
+        torrent.promote_tracker("http://c");
+        assert_eq!(
+            torrent.tiers()[0],
+            vec!["http://c".to_string(), "http://b".to_string(), "http://a".to_string()]
+        );
+    }
+
+    #[test]
+    fn promote_tracker_is_a_noop_when_the_tracker_is_not_found() {
+        let mut torrent = sample_torrent(8, 4, 2);
+        torrent.announce_list = Some(vec![vec!["http://a".to_string()]]);
+        torrent.promote_tracker("http://missing");
+        assert_eq!(torrent.tiers()[0], vec!["http://a".to_string()]);
+    }
+}
@@ -0,0 +1,129 @@
+//! UDP tracker protocol client (BEP 15), used as a fallback for `udp://`
+//! announce URLs that the HTTP(S) tracker client in `peers` can't speak to.
+
+use anyhow::{bail, Context, Result};
+use rand::random;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+const PROTOCOL_ID: u64 = 0x41727101980;
+const ACTION_CONNECT: u32 = 0;
+const ACTION_ANNOUNCE: u32 = 1;
+const MAX_RETRIES: u32 = 8;
+
+const PEER_ID: &[u8; 20] = b"00112233445566778899";
+
+/// Performs the BEP 15 connect/announce handshake against a `udp://`
+/// tracker and returns its peer list.
+pub async fn announce(tracker_url: &str, info_hash: &[u8; 20], left: u64) -> Result<Vec<SocketAddrV4>> {
+    let host = tracker_url
+        .strip_prefix("udp://")
+        .context("not a udp:// tracker url")?;
+    let host = host.split('/').next().unwrap_or(host);
+
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .context("binding udp socket")?;
+    socket
+        .connect(host)
+        .await
+        .with_context(|| format!("resolving udp tracker {host}"))?;
+
+    let connection_id = connect(&socket).await?;
+    announce_request(&socket, connection_id, info_hash, left).await
+}
+
+/// Sends `request` and waits for a reply, retransmitting on timeout with the
+/// schedule from BEP 15: 15 * 2^n seconds for n = 0..=8.
+async fn send_with_retries(socket: &UdpSocket, request: &[u8], response_buf: &mut [u8]) -> Result<usize> {
+    for n in 0..=MAX_RETRIES {
+        socket
+            .send(request)
+            .await
+            .context("sending udp tracker request")?;
+        let wait = Duration::from_secs(15 * 2u64.pow(n));
+        match timeout(wait, socket.recv(response_buf)).await {
+            Ok(Ok(len)) => return Ok(len),
+            Ok(Err(err)) => return Err(err).context("receiving udp tracker response"),
+            Err(_) => continue,
+        }
+    }
+    bail!("udp tracker did not respond after {MAX_RETRIES} retries")
+}
+
+async fn connect(socket: &UdpSocket) -> Result<u64> {
+    let transaction_id: u32 = random();
+    let mut request = Vec::with_capacity(16);
+    request.extend_from_slice(&PROTOCOL_ID.to_be_bytes());
+    request.extend_from_slice(&ACTION_CONNECT.to_be_bytes());
+    request.extend_from_slice(&transaction_id.to_be_bytes());
+
+    let mut response = [0u8; 16];
+    let len = send_with_retries(socket, &request, &mut response).await?;
+    if len < 16 {
+        bail!("udp tracker connect response too short ({len} bytes)");
+    }
+
+    let action = u32::from_be_bytes(response[0..4].try_into().unwrap());
+    let received_transaction_id = u32::from_be_bytes(response[4..8].try_into().unwrap());
+    if action != ACTION_CONNECT {
+        bail!("expected connect action ({ACTION_CONNECT}), got {action}");
+    }
+    if received_transaction_id != transaction_id {
+        bail!("udp tracker connect transaction id mismatch");
+    }
+
+    Ok(u64::from_be_bytes(response[8..16].try_into().unwrap()))
+}
+
+async fn announce_request(
+    socket: &UdpSocket,
+    connection_id: u64,
+    info_hash: &[u8; 20],
+    left: u64,
+) -> Result<Vec<SocketAddrV4>> {
+    let transaction_id: u32 = random();
+    let mut request = Vec::with_capacity(98);
+    request.extend_from_slice(&connection_id.to_be_bytes());
+    request.extend_from_slice(&ACTION_ANNOUNCE.to_be_bytes());
+    request.extend_from_slice(&transaction_id.to_be_bytes());
+    request.extend_from_slice(info_hash);
+    request.extend_from_slice(PEER_ID);
+    request.extend_from_slice(&0u64.to_be_bytes()); // downloaded
+    request.extend_from_slice(&left.to_be_bytes());
+    request.extend_from_slice(&0u64.to_be_bytes()); // uploaded
+    request.extend_from_slice(&0u32.to_be_bytes()); // event: none
+    request.extend_from_slice(&0u32.to_be_bytes()); // ip: default
+    request.extend_from_slice(&random::<u32>().to_be_bytes()); // key
+    request.extend_from_slice(&(-1i32).to_be_bytes()); // num_want: default
+    request.extend_from_slice(&6881u16.to_be_bytes()); // port
+
+    let mut response = [0u8; 4096];
+    let len = send_with_retries(socket, &request, &mut response).await?;
+    if len < 20 {
+        bail!("udp tracker announce response too short ({len} bytes)");
+    }
+
+    let action = u32::from_be_bytes(response[0..4].try_into().unwrap());
+    let received_transaction_id = u32::from_be_bytes(response[4..8].try_into().unwrap());
+    if action != ACTION_ANNOUNCE {
+        bail!("expected announce action ({ACTION_ANNOUNCE}), got {action}");
+    }
+    if received_transaction_id != transaction_id {
+        bail!("udp tracker announce transaction id mismatch");
+    }
+    // response[8..12] = interval, response[12..16] = leechers, response[16..20] = seeders
+
+    Ok(response[20..len]
+        .chunks(6)
+        .filter(|chunk| chunk.len() == 6)
+        .map(|chunk| {
+            SocketAddrV4::new(
+                Ipv4Addr::new(chunk[0], chunk[1], chunk[2], chunk[3]),
+                u16::from_be_bytes([chunk[4], chunk[5]]),
+            )
+        })
+        .collect())
+}
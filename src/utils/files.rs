@@ -0,0 +1,133 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::structs::torrent::{FileInfo, Info, Keys};
+
+pub fn write_file(output: &str, data: &[u8]) -> Result<()> {
+    if let Some(parent) = Path::new(output).parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).context("creating output directory")?;
+        }
+    }
+    fs::write(output, data).context("writing output file")?;
+    Ok(())
+}
+
+/// Pushes a single untrusted path component onto `path`, rejecting anything
+/// that isn't a plain file/directory name: empty components, `.`/`..`, an
+/// embedded separator, or an absolute path would otherwise let a malicious
+/// torrent (or magnet-derived `Info`) write outside of the intended output
+/// directory (a zip-slip-style arbitrary file write).
+fn push_safe_component(path: &mut PathBuf, component: &str) -> Result<()> {
+    let mut parts = Path::new(component).components();
+    match (parts.next(), parts.next()) {
+        (Some(Component::Normal(part)), None) => {
+            path.push(part);
+            Ok(())
+        }
+        _ => bail!("rejecting unsafe path component {component:?}"),
+    }
+}
+
+fn sanitized_file_path(base: &Path, file: &FileInfo) -> Result<PathBuf> {
+    let mut path = base.to_path_buf();
+    for component in &file.path {
+        push_safe_component(&mut path, component)
+            .with_context(|| format!("in torrent file path {:?}", file.path))?;
+    }
+    Ok(path)
+}
+
+/// Writes a downloaded torrent's assembled contiguous byte stream to disk.
+/// For a single-file torrent this is just `write_file(output, data)`. For a
+/// multi-file torrent, `data` is split at each file's boundary and written
+/// to `output/<name>/<path components>`, creating directories as needed.
+pub fn write_torrent_output(output: &str, info: &Info, data: &[u8]) -> Result<()> {
+    match &info.keys {
+        Keys::SingleFile { .. } => write_file(output, data),
+        Keys::MultiFile { files } => {
+            let mut base = PathBuf::from(output);
+            push_safe_component(&mut base, &info.name)
+                .with_context(|| format!("torrent name {:?} is not a safe directory name", info.name))?;
+
+            let mut offset = 0usize;
+            for file in files {
+                let end = offset + file.length as usize;
+                let bytes = data
+                    .get(offset..end)
+                    .with_context(|| format!("torrent data too short for file at offset {offset}"))?;
+
+                let path = sanitized_file_path(&base, file)?;
+                let path = path.to_str().context("non-utf8 output path")?;
+                write_file(path, bytes)?;
+
+                offset = end;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::torrent::FileInfo;
+
+    fn unique_temp_dir(name: &str) -> PathBuf {
+        let mut dir = std::env::temp_dir();
+        dir.push(format!(
+            "bittorrent-starter-rust-test-{name}-{:?}",
+            std::thread::current().id()
+        ));
+        dir
+    }
+
+    #[test]
+    fn write_torrent_output_splits_multi_file_data_at_file_boundaries() {
+        let dir = unique_temp_dir("multi-file-split");
+        let _ = fs::remove_dir_all(&dir);
+
+        let info = Info {
+            keys: Keys::MultiFile {
+                files: vec![
+                    FileInfo { length: 3, path: vec!["a.txt".to_string()] },
+                    FileInfo {
+                        length: 4,
+                        path: vec!["sub".to_string(), "b.txt".to_string()],
+                    },
+                ],
+            },
+            name: "bundle".to_string(),
+            piece_length: 4,
+            pieces: serde_bytes::ByteBuf::from(vec![0u8; 20]),
+        };
+
+        write_torrent_output(dir.to_str().unwrap(), &info, b"abcWXYZ").unwrap();
+
+        assert_eq!(fs::read(dir.join("bundle").join("a.txt")).unwrap(), b"abc");
+        assert_eq!(fs::read(dir.join("bundle").join("sub").join("b.txt")).unwrap(), b"WXYZ");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_torrent_output_rejects_a_traversal_attempt_in_a_file_path() {
+        let dir = unique_temp_dir("traversal");
+        let _ = fs::remove_dir_all(&dir);
+
+        let info = Info {
+            keys: Keys::MultiFile {
+                files: vec![FileInfo {
+                    length: 3,
+                    path: vec!["..".to_string(), "evil.txt".to_string()],
+                }],
+            },
+            name: "bundle".to_string(),
+            piece_length: 4,
+            pieces: serde_bytes::ByteBuf::from(vec![0u8; 20]),
+        };
+
+        assert!(write_torrent_output(dir.to_str().unwrap(), &info, b"bad").is_err());
+    }
+}